@@ -115,6 +115,100 @@ impl Peripheral {
     }
 }
 
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const RING_CAPACITY: usize = 64;
+
+// A single-producer/single-consumer ring buffer after the pattern embassy
+// uses for its UART: a fixed backing store with atomic start/end/len indices
+// so that `push`/`pop` take `&self` and the buffer can live behind a shared
+// reference. Exactly one writer (the host side) and one reader (the core side)
+// may touch it, even across interrupt priority levels.
+struct RingBuffer {
+    buf: UnsafeCell<[u8; RING_CAPACITY]>,
+    start: AtomicUsize,
+    end: AtomicUsize,
+    len: AtomicUsize,
+}
+
+// Safe because access is restricted to one producer and one consumer; the
+// atomic len with Acquire/Release ordering publishes each byte before it can
+// be observed as readable.
+unsafe impl Sync for RingBuffer {}
+
+impl RingBuffer {
+    const fn new() -> RingBuffer {
+        RingBuffer {
+            buf: UnsafeCell::new([0u8; RING_CAPACITY]),
+            start: AtomicUsize::new(0),
+            end: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+    // Producer side: enqueue a byte, returning false when the buffer is full.
+    fn push(&self, byte: u8) -> bool {
+        if self.len.load(Ordering::Acquire) >= RING_CAPACITY {
+            return false;
+        }
+        let end = self.end.load(Ordering::Relaxed);
+        unsafe { (*self.buf.get())[end] = byte; }
+        self.end.store((end + 1) % RING_CAPACITY, Ordering::Relaxed);
+        self.len.fetch_add(1, Ordering::Release);
+        true
+    }
+    // Consumer side: dequeue a byte, or None when the buffer is empty.
+    fn pop(&self) -> Option<u8> {
+        if self.len.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        let start = self.start.load(Ordering::Relaxed);
+        let byte = unsafe { (*self.buf.get())[start] };
+        self.start.store((start + 1) % RING_CAPACITY, Ordering::Relaxed);
+        self.len.fetch_sub(1, Ordering::Release);
+        Some(byte)
+    }
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for RingBuffer {
+    fn default() -> RingBuffer { RingBuffer::new() }
+}
+
+// A serial device sitting on the ring buffer: the host feeds received bytes in
+// and, once the backlog crosses the watermark, the port requests an interrupt
+// through the peripheral interrupt controller at its configured priority and
+// vector.
+struct SerialPort {
+    rx: RingBuffer,
+    peripheral: Peripheral,
+    watermark: usize,
+}
+
+impl SerialPort {
+    fn new(priority: u8, vector: u8, watermark: usize) -> SerialPort {
+        SerialPort {
+            rx: RingBuffer::new(),
+            peripheral: Peripheral::vectored(priority, vector),
+            watermark: watermark,
+        }
+    }
+    // Host side: buffer a received byte and assert the interrupt when the
+    // watermark is reached. Returns false if the buffer was full.
+    fn receive<'a>(&'a self, byte: u8, int_ctrl: &mut PeriperhalInterruptController<'a>) -> bool {
+        let accepted = self.rx.push(byte);
+        if self.rx.len() >= self.watermark {
+            int_ctrl.request_interrupt(&self.peripheral);
+        }
+        accepted
+    }
+}
+
 struct AutoInterruptController {
     level: u8
 }
@@ -139,8 +233,8 @@ impl InterruptController for AutoInterruptController {
 
 #[cfg(test)]
 mod tests {
-    use super::{FakeCore, InterruptController, PeriperhalInterruptController, AutoInterruptController, Peripheral, 
-        AUTOVECTOR_BASE, UNINITIALIZED_INTERRUPT};
+    use super::{FakeCore, InterruptController, PeriperhalInterruptController, AutoInterruptController, Peripheral,
+        SerialPort, AUTOVECTOR_BASE, UNINITIALIZED_INTERRUPT};
 
     fn assert_auto<T: InterruptController>(core: &mut FakeCore<T>, prio: u8) {
         assert_next(core, prio, if prio > 0 {Some(AUTOVECTOR_BASE + prio)} else {None})
@@ -222,6 +316,31 @@ mod tests {
         core.process_interrupt();
         assert_eq!(Some(AUTOVECTOR_BASE + 7), core.vector);
     }
+    #[test]
+    fn serial_port_drives_interrupt() {
+        let serial = SerialPort::new(4, 70, 2);
+        let int_ctrl = PeriperhalInterruptController {
+            highest_priority: 0,
+            asserted: [None, None, None, None, None, None, None]
+        };
+        let mut core = FakeCore::new(0, 0, int_ctrl);
+
+        // a single byte is below the watermark, so nothing is asserted yet
+        assert!(serial.receive(b'h', &mut core.int_ctrl));
+        assert_eq!(0, core.int_ctrl.highest_priority());
+
+        // crossing the watermark raises the serial port's interrupt
+        assert!(serial.receive(b'i', &mut core.int_ctrl));
+        assert_eq!(4, core.int_ctrl.highest_priority());
+        core.process_interrupt();
+        assert_eq!(Some(70), core.vector);
+
+        // the bytes the host queued are still there for the core to drain
+        assert_eq!(Some(b'h'), serial.rx.pop());
+        assert_eq!(Some(b'i'), serial.rx.pop());
+        assert!(serial.rx.is_empty());
+    }
+
     #[test]
     fn nonmaskable_interrupts_in_progress() {
         let auto_ctrl = AutoInterruptController { level: 0 };